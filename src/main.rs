@@ -3,23 +3,28 @@ use regex::bytes::{Captures, Regex, RegexSet};
 use serde::{de::Error, Deserialize, Deserializer};
 use serde_derive::Deserialize;
 use std::{
+    borrow::Cow,
     cmp::Reverse,
     collections::BTreeMap,
     error,
     fs::{self, File},
-    io::{BufRead, BufReader, stdout, Write},
+    io::{stdin, stdout, BufRead, BufReader, Write},
     path::PathBuf,
-    process, str,
-    borrow::Cow,
+    process, str, thread,
+    time::Duration as StdDuration,
 };
-use structopt::StructOpt;
-
-struct Interval {
-    start: NaiveDateTime,
-    end: NaiveDateTime,
-    message: Vec<u8>,
-    color: usize,
-    lane: usize,
+use structopt::{clap::arg_enum, StructOpt};
+
+mod render;
+
+use render::{HtmlRenderer, JsonRenderer, LaneLayout, Renderer, SvgRenderer};
+
+pub(crate) struct Interval {
+    pub(crate) start: NaiveDateTime,
+    pub(crate) end: NaiveDateTime,
+    pub(crate) message: Vec<u8>,
+    pub(crate) color: usize,
+    pub(crate) lane: usize,
 }
 
 #[derive(Debug, Deserialize)]
@@ -29,10 +34,27 @@ struct TimestampPattern {
     format: String,
 }
 
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum DurationFormat {
+    /// Named capture groups `h`/`m`/`s`, each a float, e.g. `h=1 m=30 s=0`.
+    HourMinSec,
+    /// A single `dur` capture group holding a humantime-style string, e.g. `1h30m` or `500ms`.
+    Humantime,
+}
+
+impl Default for DurationFormat {
+    fn default() -> Self {
+        DurationFormat::HourMinSec
+    }
+}
+
 #[derive(Debug, Deserialize)]
 struct DurationPattern {
     #[serde(deserialize_with = "deserialize_regex")]
     pattern: Regex,
+    #[serde(default)]
+    format: DurationFormat,
 }
 
 #[derive(Debug, Deserialize)]
@@ -43,11 +65,40 @@ struct ColorPattern {
     group: usize,
 }
 
+#[derive(Debug, Deserialize)]
+struct FilterPattern {
+    name: String,
+    #[serde(deserialize_with = "deserialize_regex")]
+    pattern: Regex,
+    #[serde(default)]
+    severity_group: Option<String>,
+}
+
+fn default_severity_levels() -> Vec<String> {
+    ["trace", "debug", "info", "warn", "error"]
+        .iter()
+        .map(|&s| s.to_string())
+        .collect()
+}
+
 #[derive(Debug, Deserialize)]
 struct Config {
     timestamp: TimestampPattern,
     durations: Vec<DurationPattern>,
     colors: Vec<ColorPattern>,
+    #[serde(default)]
+    filters: Vec<FilterPattern>,
+    #[serde(default = "default_severity_levels")]
+    severity_levels: Vec<String>,
+}
+
+arg_enum! {
+    #[derive(Debug, Clone, Copy)]
+    enum OutputFormat {
+        Html,
+        Svg,
+        Json,
+    }
 }
 
 #[derive(StructOpt)]
@@ -57,6 +108,31 @@ struct Args {
 
     #[structopt(parse(from_os_str))]
     log: PathBuf,
+
+    /// Only keep lines matching a named selector at or above the given severity, e.g. `--filter http:warn`
+    #[structopt(long = "filter")]
+    filters: Vec<String>,
+
+    /// Output format for the rendered timeline
+    #[structopt(
+        long,
+        default_value = "html",
+        possible_values = &OutputFormat::variants(),
+        case_insensitive = true
+    )]
+    format: OutputFormat,
+
+    /// Keep reading `log` as it grows (or from stdin if `log` is `-`) instead of
+    /// processing it once to completion, emitting one JSON interval per line as
+    /// new matches are found
+    #[structopt(long)]
+    follow: bool,
+
+    /// Drop intervals shorter than this, as a humantime-style string (e.g. `500ms`,
+    /// `2.5us`); `0s` keeps everything. Defaults to `0s` so millisecond- and
+    /// microsecond-scale intervals aren't silently discarded.
+    #[structopt(long, default_value = "0s")]
+    min_duration: String,
 }
 
 fn deserialize_regex<'de, D: Deserializer<'de>>(de: D) -> Result<Regex, D::Error> {
@@ -75,310 +151,460 @@ fn get_float(c: &Captures, name: &str) -> f64 {
         .unwrap_or(0.0)
 }
 
-fn parse_duration(captures: &Captures) -> Option<Duration> {
-    let hours = get_float(captures, "h");
-    let minutes = get_float(captures, "m");
-    let seconds = get_float(captures, "s");
-    let nanoseconds = (hours * 3600.0 + minutes * 60.0 + seconds) * 1e9;
-    Some(Duration::nanoseconds(nanoseconds.round() as i64))
+fn get_named_str<'t>(c: &Captures<'t>, name: &str) -> Option<&'t str> {
+    str::from_utf8(c.name(name)?.as_bytes()).ok()
 }
 
-fn escape_js(s: &str) -> Cow<'_, str> {
+fn parse_filter_arg(arg: &str) -> Result<(String, String), String> {
+    let mut parts = arg.splitn(2, ':');
+    match (parts.next(), parts.next()) {
+        (Some(tag), Some(level)) if !tag.is_empty() && !level.is_empty() => {
+            Ok((tag.to_string(), level.to_string()))
+        }
+        _ => Err(format!(
+            "invalid --filter value {:?}, expected TAG:LEVEL",
+            arg
+        )),
+    }
+}
+
+/// Resolves a `--filter TAG:LEVEL` argument against the configured `filters` and
+/// `severity_levels`, so an unrecognized tag or level is reported as a startup error
+/// instead of silently matching nothing.
+fn resolve_filter_selector(
+    arg: &str,
+    filters: &[FilterPattern],
+    severity_levels: &[String],
+) -> Result<(usize, usize), String> {
+    let (tag, level) = parse_filter_arg(arg)?;
+    let filter_index = filters
+        .iter()
+        .position(|f| f.name == tag)
+        .ok_or_else(|| format!("--filter {:?}: no such filter {:?} in config", arg, tag))?;
+    let min_rank = severity_levels
+        .iter()
+        .position(|l| l.eq_ignore_ascii_case(&level))
+        .ok_or_else(|| {
+            format!(
+                "--filter {:?}: unknown severity level {:?}, expected one of {:?}",
+                arg, level, severity_levels
+            )
+        })?;
+    Ok((filter_index, min_rank))
+}
+
+/// Returns `true` if `line` should be kept: it must match at least one selector's
+/// filter pattern, and if a severity can be extracted for that match, it must rank at
+/// or above the selector's minimum. A selector whose filter has no `severity_group`
+/// (or whose pattern can't extract one from this line) can't be ranked, so a matching
+/// tag alone is enough. With no selectors configured, every line is kept.
+fn passes_filters(
+    matches: &regex::bytes::SetMatches,
+    line: &[u8],
+    filters: &[FilterPattern],
+    severity_levels: &[String],
+    selectors: &[(usize, usize)],
+) -> bool {
+    if selectors.is_empty() {
+        return true;
+    }
+    selectors.iter().any(|&(filter_index, min_rank)| {
+        if !matches.matched(filter_index) {
+            return false;
+        }
+        let filter = &filters[filter_index];
+        let severity_rank = filter
+            .pattern
+            .captures(line)
+            .and_then(|c| {
+                filter
+                    .severity_group
+                    .as_deref()
+                    .and_then(|g| get_named_str(&c, g))
+            })
+            .and_then(|severity| {
+                severity_levels
+                    .iter()
+                    .position(|l| l.eq_ignore_ascii_case(severity))
+            });
+        match severity_rank {
+            Some(rank) => rank >= min_rank,
+            None => true,
+        }
+    })
+}
+
+fn parse_duration(captures: &Captures, format: &DurationFormat) -> Option<Duration> {
+    match format {
+        DurationFormat::HourMinSec => {
+            let hours = get_float(captures, "h");
+            let minutes = get_float(captures, "m");
+            let seconds = get_float(captures, "s");
+            let nanoseconds = (hours * 3600.0 + minutes * 60.0 + seconds) * 1e9;
+            Some(Duration::nanoseconds(nanoseconds.round() as i64))
+        }
+        DurationFormat::Humantime => {
+            let dur_str = get_named_str(captures, "dur")?;
+            parse_humantime_duration(dur_str)
+        }
+    }
+}
+
+/// Parses a humantime-style duration string such as `1h30m`, `500ms`, or `2.5µs` by
+/// summing a run of `<number><unit>` tokens, where `unit` is one of `ns`, `us`/`µs`,
+/// `ms`, `s`, `m`, `h`, or `d`.
+fn parse_humantime_duration(s: &str) -> Option<Duration> {
+    let bytes = s.as_bytes();
+    let mut i = 0;
+    let mut total_nanoseconds = 0.0f64;
+    while i < bytes.len() {
+        while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+            i += 1;
+        }
+        if i >= bytes.len() {
+            break;
+        }
+        let number_start = i;
+        while i < bytes.len() && (bytes[i].is_ascii_digit() || bytes[i] == b'.') {
+            i += 1;
+        }
+        if i == number_start {
+            return None;
+        }
+        let number = s[number_start..i].parse::<f64>().ok()?;
+
+        let unit_start = i;
+        while i < bytes.len() && !bytes[i].is_ascii_digit() && !bytes[i].is_ascii_whitespace() {
+            i += 1;
+        }
+        let nanoseconds_per_unit = match &s[unit_start..i] {
+            "ns" => 1.0,
+            "us" | "µs" => 1e3,
+            "ms" => 1e6,
+            "s" => 1e9,
+            "m" => 6e10,
+            "h" => 3.6e12,
+            "d" => 8.64e13,
+            _ => return None,
+        };
+        total_nanoseconds += number * nanoseconds_per_unit;
+    }
+    Some(Duration::nanoseconds(total_nanoseconds.round() as i64))
+}
+
+pub(crate) fn escape_js(s: &str) -> Cow<'_, str> {
     lazy_static::lazy_static! {
         static ref PATTERN: regex::Regex = regex::Regex::new("['\\\\\r\n]").unwrap();
     }
-    PATTERN.replace_all(s, |c: &regex::Captures| {
-        match c.get(0).unwrap().as_str() {
-            r"'" => r"\'",
-            r"\" => r"\\",
-            "\r" => r"\r",
-            "\n" => r"\n",
-            _ => unreachable!(),
-        }
+    PATTERN.replace_all(s, |c: &regex::Captures| match c.get(0).unwrap().as_str() {
+        r"'" => r"\'",
+        r"\" => r"\\",
+        "\r" => r"\r",
+        "\n" => r"\n",
+        _ => unreachable!(),
     })
 }
 
 const LANE_WIDTH: usize = 20;
 const MIN_GLOBAL_WIDTH: usize = 400;
 
+/// Packs intervals into per-color-group lanes, closing a lane once its last interval
+/// ended at least `lane_gap` ago so a new interval can reuse it instead of opening a new one.
+struct LaneAssigner {
+    lane_gap: Duration,
+    groups: BTreeMap<usize, Vec<NaiveDateTime>>,
+}
+
+impl LaneAssigner {
+    fn new(colors: &[ColorPattern], lane_gap: Duration) -> Self {
+        let groups = colors.iter().map(|c| (c.group, Vec::new())).collect();
+        LaneAssigner { lane_gap, groups }
+    }
+
+    fn assign(&mut self, group: usize, interval: &mut Interval) {
+        let lane_gap = self.lane_gap;
+        let color_lanes = self.groups.get_mut(&group).unwrap();
+        if let Some((lane_end_time, lane_id)) = color_lanes
+            .iter_mut()
+            .zip(0..)
+            .filter(|(e, _)| interval.start - **e >= lane_gap)
+            .next()
+        {
+            *lane_end_time = interval.end;
+            interval.lane = lane_id;
+        } else {
+            interval.lane = color_lanes.len();
+            color_lanes.push(interval.end);
+        }
+    }
+
+    fn total_lanes(&self) -> usize {
+        self.groups.values().map(Vec::len).sum()
+    }
+
+    /// The absolute lane offset each color group's lanes start at, once all groups'
+    /// lane counts are known.
+    fn group_offsets(&self) -> BTreeMap<usize, usize> {
+        let mut total = 0;
+        self.groups
+            .iter()
+            .map(|(&group, lanes)| {
+                let offset = total;
+                total += lanes.len();
+                (group, offset)
+            })
+            .collect()
+    }
+}
+
+/// Matches `line` against the configured duration/timestamp/color/filter patterns and
+/// returns the `Interval` it describes, or `None` if the line doesn't qualify (no
+/// duration/timestamp match, rejected by the `--filter` selectors, or shorter than
+/// `min_duration`).
+fn try_parse_interval(
+    line: Vec<u8>,
+    config: &Config,
+    filter_regex_set: &RegexSet,
+    filter_selectors: &[(usize, usize)],
+    color_regex_set: &RegexSet,
+    min_duration: Duration,
+) -> Result<Option<Interval>, Box<dyn error::Error>> {
+    let dur_match = config
+        .durations
+        .iter()
+        .find_map(|d| d.pattern.captures(&line).map(|c| (c, &d.format)));
+    let (dur_captures, dur_format) = match dur_match {
+        Some(m) => m,
+        None => return Ok(None),
+    };
+    let ts_captures = match config.timestamp.pattern.captures(&line) {
+        Some(c) => c,
+        None => return Ok(None),
+    };
+    let end_ts = get_str(&ts_captures, 1).unwrap();
+    let end_ts = NaiveDateTime::parse_from_str(end_ts, &config.timestamp.format).or_else(|_| {
+        NaiveTime::parse_from_str(end_ts, &config.timestamp.format)
+            .map(|t| NaiveDate::from_ymd(1, 1, 1).and_time(t))
+    })?;
+    let dur = match parse_duration(&dur_captures, dur_format) {
+        Some(dur) => dur,
+        None => return Ok(None),
+    };
+    if !passes_filters(
+        &filter_regex_set.matches(&line),
+        &line,
+        &config.filters,
+        &config.severity_levels,
+        filter_selectors,
+    ) {
+        return Ok(None);
+    }
+    if dur < min_duration {
+        return Ok(None);
+    }
+    let color = color_regex_set
+        .matches(&line)
+        .iter()
+        .next()
+        .ok_or_else(|| format!("no color specified for {}", String::from_utf8_lossy(&line)))?;
+    let start_ts = end_ts - dur;
+    Ok(Some(Interval {
+        start: start_ts,
+        end: end_ts,
+        message: line,
+        color,
+        lane: 0,
+    }))
+}
+
 fn run() -> Result<(), Box<dyn error::Error>> {
     let args = Args::from_args();
-    let config_bytes = fs::read(args.config)?;
+    let config_bytes = fs::read(&args.config)?;
     let config = toml::from_slice::<Config>(&config_bytes)?;
 
     let color_regex_set = RegexSet::new(config.colors.iter().map(|c| &*c.pattern))?;
+    let filter_regex_set = RegexSet::new(config.filters.iter().map(|f| f.pattern.as_str()))?;
+    let filter_selectors = args
+        .filters
+        .iter()
+        .map(|s| resolve_filter_selector(s, &config.filters, &config.severity_levels))
+        .collect::<Result<Vec<_>, _>>()?;
+    let min_duration = parse_humantime_duration(&args.min_duration)
+        .ok_or_else(|| format!("invalid --min-duration value {:?}", args.min_duration))?;
+    let lane_gap = Duration::seconds(1);
+
+    if args.follow {
+        return run_follow(
+            &args,
+            &config,
+            &filter_regex_set,
+            &filter_selectors,
+            &color_regex_set,
+            min_duration,
+            lane_gap,
+        );
+    }
 
+    run_batch(
+        &args,
+        &config,
+        &filter_regex_set,
+        &filter_selectors,
+        &color_regex_set,
+        min_duration,
+        lane_gap,
+    )
+}
+
+fn run_batch(
+    args: &Args,
+    config: &Config,
+    filter_regex_set: &RegexSet,
+    filter_selectors: &[(usize, usize)],
+    color_regex_set: &RegexSet,
+    min_duration: Duration,
+    lane_gap: Duration,
+) -> Result<(), Box<dyn error::Error>> {
     let mut intervals = Vec::new();
-    let cutoff = Duration::seconds(1);
 
-    let log_file = BufReader::new(File::open(args.log)?);
+    let log_file = BufReader::new(File::open(&args.log)?);
 
     let mut global_start_time = chrono::naive::MAX_DATE.and_hms_nano(23, 59, 59, 999_999_999);
     let mut global_end_time = chrono::naive::MIN_DATE.and_hms(0, 0, 0);
 
     for line in log_file.split(b'\n') {
         let line = line?;
-        if let Some(dur_captures) = config
-            .durations
-            .iter()
-            .flat_map(|d| d.pattern.captures(&line))
-            .next()
-        {
-            if let Some(ts_captures) = config.timestamp.pattern.captures(&line) {
-                let end_ts = get_str(&ts_captures, 1).unwrap();
-                let end_ts = NaiveDateTime::parse_from_str(end_ts, &config.timestamp.format)
-                    .or_else(|_| {
-                        NaiveTime::parse_from_str(end_ts, &config.timestamp.format)
-                            .map(|t| NaiveDate::from_ymd(1, 1, 1).and_time(t))
-                    })?;
-                if let Some(dur) = parse_duration(&dur_captures) {
-                    if dur < cutoff {
-                        continue;
-                    }
-                    let color = color_regex_set
-                        .matches(&line)
-                        .iter()
-                        .next()
-                        .ok_or_else(|| {
-                            format!("no color specified for {}", String::from_utf8_lossy(&line))
-                        })?;
-                    let start_ts = end_ts - dur;
-                    if start_ts < global_start_time {
-                        global_start_time = start_ts;
-                    }
-                    if end_ts > global_end_time {
-                        global_end_time = end_ts;
-                    }
-                    intervals.push(Interval {
-                        start: start_ts,
-                        end: end_ts,
-                        message: line,
-                        color,
-                        lane: 0,
-                    });
-                }
+        if let Some(interval) = try_parse_interval(
+            line,
+            config,
+            filter_regex_set,
+            filter_selectors,
+            color_regex_set,
+            min_duration,
+        )? {
+            if interval.start < global_start_time {
+                global_start_time = interval.start;
+            }
+            if interval.end > global_end_time {
+                global_end_time = interval.end;
             }
+            intervals.push(interval);
         }
     }
 
-    let global_start_time = global_start_time;
-    let global_end_time = global_end_time;
-    let global_duration = (global_end_time - global_start_time).num_seconds();
+    let global_duration = (global_end_time - global_start_time)
+        .num_nanoseconds()
+        .unwrap_or(0) as f64
+        / 1e9;
 
     intervals.sort_unstable_by_key(|a| (a.start, Reverse(a.end)));
 
-    let mut lanes = config
-        .colors
-        .iter()
-        .map(|c| (c.group, (0, Vec::new())))
-        .collect::<BTreeMap<_, _>>();
+    let mut lane_assigner = LaneAssigner::new(&config.colors, lane_gap);
     for interval in &mut intervals {
         let group = config.colors[interval.color].group;
-        let color_lanes = &mut lanes.get_mut(&group).unwrap().1;
-        if let Some((lane_end_time, lane_id)) = color_lanes
-            .iter_mut()
-            .zip(0..)
-            .filter(|(e, _)| **e - interval.start < cutoff)
-            .next()
-        {
-            *lane_end_time = interval.end;
-            interval.lane = lane_id;
-        } else {
-            interval.lane = color_lanes.len();
-            color_lanes.push(interval.end);
-        }
+        lane_assigner.assign(group, interval);
     }
-
-    let mut total_lanes = 0;
-    for (start_lane_id, lanes) in lanes.values_mut() {
-        *start_lane_id = total_lanes;
-        total_lanes += lanes.len();
+    let total_lanes = lane_assigner.total_lanes();
+    let group_offsets = lane_assigner.group_offsets();
+    for interval in &mut intervals {
+        let group = config.colors[interval.color].group;
+        interval.lane += group_offsets[&group];
     }
 
+    let layout = LaneLayout {
+        lane_width: LANE_WIDTH,
+        global_width: MIN_GLOBAL_WIDTH.max(total_lanes * LANE_WIDTH),
+        global_duration,
+        global_start_time,
+        global_end_time,
+        colors: config.colors.iter().map(|c| c.color.clone()).collect(),
+    };
+
+    let renderer: Box<dyn Renderer> = match args.format {
+        OutputFormat::Html => Box::new(HtmlRenderer),
+        OutputFormat::Svg => Box::new(SvgRenderer),
+        OutputFormat::Json => Box::new(JsonRenderer),
+    };
+
     let stdout = stdout();
     let mut lock = stdout.lock();
+    renderer.render(&intervals, &layout, &mut lock)?;
 
-    writeln!(
-        lock,
-        r##"<!DOCTYPE html>
-            <html>
-                <head>
-                    <meta charset="utf8">
-                    <title>Execution timeline</title>
-                    <style>
-                        canvas {{
-                            position: absolute;
-                            left: 0.5em;
-                            top: 0.5em;
-                        }}
-                        #aux {{
-                            position: fixed;
-                            right: 0.5em;
-                            top: 0.5em;
-                            width: 30em;
-                            font-family: sans-serif;
-                            font-size: 0.75em;
-                        }}
-                    </style>
-                </head>
-                <body>
-                    <canvas id="lanes" width="{0}" height="{1}"></canvas>
-                    <canvas id="hover" width="{0}" height="{1}"></canvas>
-                    <div id="aux">
-                        <p>
-                            <label for="zoom"><strong>Zoom out:</strong></label>
-                            <input id="zoom" type="range" min="1" max="100" value="1">
-                            (<output for="zoom" id="zoom-val">1</output>Ã—)
-                        </p>
-                        <p><strong>Start time:</strong> <span id="start-time"></span></p>
-                        <p><strong>End time:</strong> <span id="end-time"></span></p>
-                        <p><strong>Message:</strong><br/><span id="msg"></span></p>
-                    </div>
-                    <script>
-                        var zoom = document.getElementById('zoom');
-                        var globalWidth = {0};
-                        var globalHeight = {1};
-                        var laneWidth = {2};
-                        var colors = [
-        "##,
-        MIN_GLOBAL_WIDTH.max(total_lanes * LANE_WIDTH),
-        global_duration,
-        LANE_WIDTH,
-    )?;
+    Ok(())
+}
 
-    for color_config in &config.colors {
-        writeln!(lock, "'{}',", escape_js(&color_config.color))?;
-    }
+/// Consumes `args.log` incrementally (polling for appended data, like `tail -f`) instead
+/// of reading it once to completion, so the tool stays useful for a build or service
+/// that's still running. Since intervals arrive without the benefit of a global sort,
+/// each newly recognized interval is flushed immediately as one line of JSON rather than
+/// waiting to re-render the whole HTML/SVG document.
+fn run_follow(
+    args: &Args,
+    config: &Config,
+    filter_regex_set: &RegexSet,
+    filter_selectors: &[(usize, usize)],
+    color_regex_set: &RegexSet,
+    min_duration: Duration,
+    lane_gap: Duration,
+) -> Result<(), Box<dyn error::Error>> {
+    let colors: Vec<String> = config.colors.iter().map(|c| c.color.clone()).collect();
+    let mut lane_assigner = LaneAssigner::new(&config.colors, lane_gap);
+    let mut global_start_time = chrono::naive::MAX_DATE.and_hms_nano(23, 59, 59, 999_999_999);
 
-    writeln!(
-        lock,
-        r##"
-                        ];
-                        var blocks = [
-        "##,
-    )?;
-
-    for interval in &intervals {
-        let top = (interval.start - global_start_time).num_seconds();
-        let height = (interval.end - interval.start).num_seconds();
-        let color_config = &config.colors[interval.color];
-        writeln!(
-            lock,
-            "{{color: {}, start: '{}', end: '{}', msg: '{}', top: {}, height: {}, lane: {}}},",
-            interval.color,
-            interval.start,
-            interval.end,
-            escape_js(&String::from_utf8_lossy(&interval.message)),
-            top,
-            height,
-            interval.lane + lanes[&color_config.group].0,
-        )?;
-    }
+    let stdin = stdin();
+    let mut reader: Box<dyn BufRead> = if args.log.as_os_str() == "-" {
+        Box::new(stdin.lock())
+    } else {
+        Box::new(BufReader::new(File::open(&args.log)?))
+    };
 
-    writeln!(
-        lock,
-        "{}",
-        r##"
-                        ];
-                        function render(z) {
-                            var ctx = document.getElementById('lanes').getContext('2d');
-                            ctx.clearRect(0, 0, globalWidth, globalHeight);
-
-                            ctx.lineWidth = 1;
-                            ctx.font = 'sans-serif';
-                            ctx.textBaseline = 'top';
-                            ctx.textAlign = 'right';
-                            ctx.fillStyle = '#999';
-                            for (var i = 0; i < globalHeight; i += 300) {
-                                var notHour = i % 3600;
-                                var x = notHour ? 0.85 : 0.75;
-                                var y = Math.round(i * z) + 0.5;
-                                ctx.strokeStyle = notHour ? '#999' : '#333';
-                                ctx.beginPath();
-                                ctx.moveTo(globalWidth*x, y);
-                                ctx.lineTo(globalWidth, y);
-                                ctx.stroke();
-                                ctx.fillText((i/60|0) + 'm', globalWidth, y);
-                            }
-
-                            for (var i = 0, block; block = blocks[i]; ++ i) {
-                                ctx.fillStyle = colors[block.color];
-                                ctx.fillRect(
-                                    block.lane * laneWidth,
-                                    block.top * z,
-                                    laneWidth - 1,
-                                    block.height * z,
-                                );
-                            }
-                        }
-                        document.addEventListener('DOMContentLoaded', function() {
-                            render(1);
-                        });
-                        zoom.addEventListener('input', function() {
-                            document.getElementById('zoom-val').value = zoom.value;
-                            render(1/zoom.value);
-                        });
-
-                        document.getElementById('hover').addEventListener('mousemove', function(e) {
-                            var rect = this.getBoundingClientRect();
-                            var z = 1/zoom.value;
-                            var xx = e.clientX - rect.left;
-                            var yy = e.clientY - rect.top;
-                            var x = xx / laneWidth;
-                            var y = yy / z;
-                            yy = Math.round(yy) + 0.5;
-
-                            // FIXME: Consider switching to a spatial data structure to speed up searching
-                            // Ref: https://stackoverflow.com/questions/7727758/find-overlapping-rectangles-algorithm
-                            var i = 0, block;
-                            for (; block = blocks[i]; ++ i) {
-                                if (
-                                    block.top <= y && y <= block.top + block.height &&
-                                    block.lane <= x && x <= block.lane + 1
-                                ) {
-                                    break;
-                                }
-                            }
-                            if (i >= blocks.length) {
-                                i = -1;
-                            }
-
-                            var ctx = this.getContext('2d');
-                            ctx.clearRect(0, 0, globalWidth, globalHeight);
-
-                            ctx.strokeStyle = 'rgba(255,0,0,0.5)';
-                            ctx.lineWidth = 1;
-                            ctx.font = 'sans-serif';
-                            ctx.textBaseline = 'top';
-                            ctx.textAlign = 'left';
-                            ctx.fillStyle = '#f88';
-                            ctx.beginPath();
-                            ctx.moveTo(0, yy);
-                            ctx.lineTo(globalWidth, yy);
-                            ctx.stroke();
-                            ctx.fillText((y/60|0) + 'm' + (y%60|0) + 's', globalWidth * 0.85, yy);
-
-                            if (i !== -1) {
-                                var block = blocks[i];
-                                ctx.strokeStyle = '#000';
-                                ctx.strokeRect(
-                                    block.lane * laneWidth,
-                                    block.top * z,
-                                    laneWidth - 1,
-                                    block.height * z,
-                                );
-                                document.getElementById('start-time').innerText = block.start;
-                                document.getElementById('end-time').innerText = block.end;
-                                document.getElementById('msg').innerText = block.msg;
-                            }
-                        });
-                    </script>
-                </body>
-            </html>
-        "##,
-    )?;
+    let stdout = stdout();
+    let mut lock = stdout.lock();
 
-    Ok(())
+    // Accumulates across polls: `read_until` can return a trailing fragment with no
+    // `\n` yet if it hits EOF mid-line (the writer hasn't flushed the rest), so a line
+    // is only complete once it actually ends in `\n` — until then we keep appending to
+    // the same buffer instead of treating the fragment as a whole line.
+    let mut line = Vec::new();
+    loop {
+        if reader.read_until(b'\n', &mut line)? == 0 {
+            thread::sleep(StdDuration::from_millis(500));
+            continue;
+        }
+        if line.last() != Some(&b'\n') {
+            thread::sleep(StdDuration::from_millis(500));
+            continue;
+        }
+        line.pop();
+
+        let taken_line = std::mem::take(&mut line);
+        if let Some(mut interval) = try_parse_interval(
+            taken_line,
+            config,
+            filter_regex_set,
+            filter_selectors,
+            color_regex_set,
+            min_duration,
+        )? {
+            if interval.start < global_start_time {
+                global_start_time = interval.start;
+            }
+            let group = config.colors[interval.color].group;
+            lane_assigner.assign(group, &mut interval);
+            // `interval.lane` is left group-relative (not offset by group_offsets()):
+            // that offset shifts as earlier color groups open new lanes, which would
+            // reposition already-streamed blocks. Emit the group id alongside it
+            // instead so a live consumer can place each block stably.
+            render::write_json_interval_line(
+                &interval,
+                group,
+                global_start_time,
+                &colors,
+                &mut lock,
+            )?;
+            lock.flush()?;
+        }
+    }
 }
 
 fn main() {
@@ -387,3 +613,108 @@ fn main() {
         process::exit(1);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_humantime_durations() {
+        assert_eq!(
+            parse_humantime_duration("500ms"),
+            Some(Duration::milliseconds(500))
+        );
+        assert_eq!(
+            parse_humantime_duration("2.5us"),
+            Some(Duration::nanoseconds(2_500))
+        );
+        assert_eq!(
+            parse_humantime_duration("2.5\u{b5}s"),
+            Some(Duration::nanoseconds(2_500))
+        );
+        assert_eq!(
+            parse_humantime_duration("1h30m"),
+            Some(Duration::minutes(90))
+        );
+        assert_eq!(parse_humantime_duration("1d"), Some(Duration::days(1)));
+        assert_eq!(parse_humantime_duration("bogus"), None);
+    }
+
+    #[test]
+    fn parses_filter_args() {
+        assert_eq!(
+            parse_filter_arg("http:warn"),
+            Ok(("http".to_string(), "warn".to_string()))
+        );
+        assert!(parse_filter_arg("http").is_err());
+        assert!(parse_filter_arg(":warn").is_err());
+        assert!(parse_filter_arg("http:").is_err());
+    }
+
+    fn http_filter() -> FilterPattern {
+        FilterPattern {
+            name: "http".to_string(),
+            pattern: Regex::new(r"(?P<level>trace|debug|info|warn|error) http").unwrap(),
+            severity_group: Some("level".to_string()),
+        }
+    }
+
+    #[test]
+    fn passes_filters_keeps_only_matching_selectors() {
+        let filters = vec![http_filter()];
+        let severity_levels = default_severity_levels();
+        let regex_set = RegexSet::new(filters.iter().map(|f| f.pattern.as_str())).unwrap();
+        let selectors =
+            vec![resolve_filter_selector("http:warn", &filters, &severity_levels).unwrap()];
+
+        let warn_line = b"warn http request failed";
+        assert!(passes_filters(
+            &regex_set.matches(warn_line),
+            warn_line,
+            &filters,
+            &severity_levels,
+            &selectors,
+        ));
+
+        let info_line = b"info http request ok";
+        assert!(!passes_filters(
+            &regex_set.matches(info_line),
+            info_line,
+            &filters,
+            &severity_levels,
+            &selectors,
+        ));
+
+        let unrelated_line = b"db query finished";
+        assert!(!passes_filters(
+            &regex_set.matches(unrelated_line),
+            unrelated_line,
+            &filters,
+            &severity_levels,
+            &selectors,
+        ));
+    }
+
+    #[test]
+    fn passes_filters_keeps_everything_with_no_selectors() {
+        let filters = vec![http_filter()];
+        let severity_levels = default_severity_levels();
+        let regex_set = RegexSet::new(filters.iter().map(|f| f.pattern.as_str())).unwrap();
+        let line = b"db query finished";
+        assert!(passes_filters(
+            &regex_set.matches(line),
+            line,
+            &filters,
+            &severity_levels,
+            &[],
+        ));
+    }
+
+    #[test]
+    fn resolve_filter_selector_rejects_unknown_tag_or_level() {
+        let filters = vec![http_filter()];
+        let severity_levels = default_severity_levels();
+        assert!(resolve_filter_selector("db:warn", &filters, &severity_levels).is_err());
+        assert!(resolve_filter_selector("http:critical", &filters, &severity_levels).is_err());
+    }
+}
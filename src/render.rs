@@ -0,0 +1,395 @@
+use crate::{escape_js, Interval};
+use chrono::NaiveDateTime;
+use serde_derive::Serialize;
+use std::{borrow::Cow, collections::BTreeMap, error, io::Write};
+
+/// Geometry and styling shared by every renderer: where intervals sit on the lane
+/// grid and what time range they span. Lane indices on `Interval` are already
+/// absolute (offset into the shared grid), so renderers don't need `Config` at all.
+pub(crate) struct LaneLayout {
+    pub(crate) lane_width: usize,
+    pub(crate) global_width: usize,
+    pub(crate) global_duration: f64,
+    pub(crate) global_start_time: NaiveDateTime,
+    pub(crate) global_end_time: NaiveDateTime,
+    pub(crate) colors: Vec<String>,
+}
+
+/// Fractional seconds between two timestamps, so millisecond- and microsecond-scale
+/// intervals don't collapse to zero height the way whole-second truncation would.
+fn seconds_between(start: NaiveDateTime, end: NaiveDateTime) -> f64 {
+    (end - start).num_nanoseconds().unwrap_or(0) as f64 / 1e9
+}
+
+pub(crate) trait Renderer {
+    fn render(
+        &self,
+        intervals: &[Interval],
+        layout: &LaneLayout,
+        w: &mut dyn Write,
+    ) -> Result<(), Box<dyn error::Error>>;
+}
+
+/// The original inline HTML document with a `<canvas>` timeline and hover inspector.
+pub(crate) struct HtmlRenderer;
+
+impl Renderer for HtmlRenderer {
+    fn render(
+        &self,
+        intervals: &[Interval],
+        layout: &LaneLayout,
+        w: &mut dyn Write,
+    ) -> Result<(), Box<dyn error::Error>> {
+        writeln!(
+            w,
+            r##"<!DOCTYPE html>
+            <html>
+                <head>
+                    <meta charset="utf8">
+                    <title>Execution timeline</title>
+                    <style>
+                        canvas {{
+                            position: absolute;
+                            left: 0.5em;
+                            top: 0.5em;
+                        }}
+                        #aux {{
+                            position: fixed;
+                            right: 0.5em;
+                            top: 0.5em;
+                            width: 30em;
+                            font-family: sans-serif;
+                            font-size: 0.75em;
+                        }}
+                    </style>
+                </head>
+                <body>
+                    <canvas id="lanes" width="{0}" height="{1}"></canvas>
+                    <canvas id="hover" width="{0}" height="{1}"></canvas>
+                    <div id="aux">
+                        <p>
+                            <label for="zoom"><strong>Zoom out:</strong></label>
+                            <input id="zoom" type="range" min="1" max="100" value="1">
+                            (<output for="zoom" id="zoom-val">1</output>Ã—)
+                        </p>
+                        <p><strong>Start time:</strong> <span id="start-time"></span></p>
+                        <p><strong>End time:</strong> <span id="end-time"></span></p>
+                        <p><strong>Message:</strong><br/><span id="msg"></span></p>
+                    </div>
+                    <script>
+                        var zoom = document.getElementById('zoom');
+                        var globalWidth = {0};
+                        var globalHeight = {1};
+                        var laneWidth = {2};
+                        var colors = [
+        "##,
+            layout.global_width,
+            layout.global_duration.ceil() as i64,
+            layout.lane_width,
+        )?;
+
+        for color in &layout.colors {
+            writeln!(w, "'{}',", escape_js(color))?;
+        }
+
+        writeln!(
+            w,
+            r##"
+                        ];
+                        var blocks = [
+        "##,
+        )?;
+
+        let mut lane_index: BTreeMap<usize, Vec<(f64, f64, usize)>> = BTreeMap::new();
+        for (block_id, interval) in intervals.iter().enumerate() {
+            let top = seconds_between(layout.global_start_time, interval.start);
+            let height = seconds_between(interval.start, interval.end);
+            writeln!(
+                w,
+                "{{color: {}, start: '{}', end: '{}', msg: '{}', top: {}, height: {}, lane: {}}},",
+                interval.color,
+                interval.start,
+                interval.end,
+                escape_js(&String::from_utf8_lossy(&interval.message)),
+                top,
+                height,
+                interval.lane,
+            )?;
+            // Lane assignment guarantees intervals sharing a lane never overlap in
+            // time, and they're already visited here in ascending `top` order.
+            lane_index
+                .entry(interval.lane)
+                .or_default()
+                .push((top, height, block_id));
+        }
+
+        writeln!(
+            w,
+            "\n                        ];\n                        var laneIndex = [];"
+        )?;
+        for (lane, entries) in &lane_index {
+            write!(w, "laneIndex[{}] = [", lane)?;
+            for (top, height, block_id) in entries {
+                write!(
+                    w,
+                    "{{top: {}, height: {}, blockId: {}}},",
+                    top, height, block_id
+                )?;
+            }
+            writeln!(w, "];")?;
+        }
+
+        writeln!(
+            w,
+            "{}",
+            r##"
+                        function formatTime(seconds) {
+                            var m = Math.floor(seconds / 60);
+                            var s = seconds - m * 60;
+                            return m + ':' + s.toFixed(3).padStart(6, '0');
+                        }
+                        function render(z) {
+                            var ctx = document.getElementById('lanes').getContext('2d');
+                            ctx.clearRect(0, 0, globalWidth, globalHeight);
+
+                            ctx.lineWidth = 1;
+                            ctx.font = 'sans-serif';
+                            ctx.textBaseline = 'top';
+                            ctx.textAlign = 'right';
+                            ctx.fillStyle = '#999';
+                            for (var i = 0; i < globalHeight; i += 300) {
+                                var notHour = i % 3600;
+                                var x = notHour ? 0.85 : 0.75;
+                                var y = Math.round(i * z) + 0.5;
+                                ctx.strokeStyle = notHour ? '#999' : '#333';
+                                ctx.beginPath();
+                                ctx.moveTo(globalWidth*x, y);
+                                ctx.lineTo(globalWidth, y);
+                                ctx.stroke();
+                                ctx.fillText(formatTime(i), globalWidth, y);
+                            }
+
+                            for (var i = 0, block; block = blocks[i]; ++ i) {
+                                ctx.fillStyle = colors[block.color];
+                                ctx.fillRect(
+                                    block.lane * laneWidth,
+                                    block.top * z,
+                                    laneWidth - 1,
+                                    block.height * z,
+                                );
+                            }
+                        }
+                        document.addEventListener('DOMContentLoaded', function() {
+                            render(1);
+                        });
+                        zoom.addEventListener('input', function() {
+                            document.getElementById('zoom-val').value = zoom.value;
+                            render(1/zoom.value);
+                        });
+
+                        document.getElementById('hover').addEventListener('mousemove', function(e) {
+                            var rect = this.getBoundingClientRect();
+                            var z = 1/zoom.value;
+                            var xx = e.clientX - rect.left;
+                            var yy = e.clientY - rect.top;
+                            var x = xx / laneWidth;
+                            var y = yy / z;
+                            yy = Math.round(yy) + 0.5;
+
+                            // Lanes never overlap in time, so binary-search the hovered
+                            // lane's sorted entries instead of scanning every block.
+                            var i = -1;
+                            var entries = laneIndex[Math.floor(x)];
+                            if (entries && entries.length) {
+                                var lo = 0, hi = entries.length - 1;
+                                while (lo < hi) {
+                                    var mid = (lo + hi + 1) >> 1;
+                                    if (entries[mid].top <= y) {
+                                        lo = mid;
+                                    } else {
+                                        hi = mid - 1;
+                                    }
+                                }
+                                var entry = entries[lo];
+                                if (entry.top <= y && y <= entry.top + entry.height) {
+                                    i = entry.blockId;
+                                }
+                            }
+
+                            var ctx = this.getContext('2d');
+                            ctx.clearRect(0, 0, globalWidth, globalHeight);
+
+                            ctx.strokeStyle = 'rgba(255,0,0,0.5)';
+                            ctx.lineWidth = 1;
+                            ctx.font = 'sans-serif';
+                            ctx.textBaseline = 'top';
+                            ctx.textAlign = 'left';
+                            ctx.fillStyle = '#f88';
+                            ctx.beginPath();
+                            ctx.moveTo(0, yy);
+                            ctx.lineTo(globalWidth, yy);
+                            ctx.stroke();
+                            ctx.fillText(formatTime(y), globalWidth * 0.85, yy);
+
+                            if (i !== -1) {
+                                var block = blocks[i];
+                                ctx.strokeStyle = '#000';
+                                ctx.strokeRect(
+                                    block.lane * laneWidth,
+                                    block.top * z,
+                                    laneWidth - 1,
+                                    block.height * z,
+                                );
+                                document.getElementById('start-time').innerText = block.start;
+                                document.getElementById('end-time').innerText = block.end;
+                                document.getElementById('msg').innerText = block.msg;
+                            }
+                        });
+                    </script>
+                </body>
+            </html>
+        "##,
+        )?;
+
+        Ok(())
+    }
+}
+
+/// Escapes text so it's safe to embed as XML character data or inside a
+/// double-quoted attribute value.
+fn escape_xml(s: &str) -> Cow<'_, str> {
+    lazy_static::lazy_static! {
+        static ref PATTERN: regex::Regex = regex::Regex::new("[&<>\"]").unwrap();
+    }
+    PATTERN.replace_all(s, |c: &regex::Captures| match c.get(0).unwrap().as_str() {
+        "&" => "&amp;",
+        "<" => "&lt;",
+        ">" => "&gt;",
+        "\"" => "&quot;",
+        _ => unreachable!(),
+    })
+}
+
+/// Static `<rect>` per interval, with no JS — good for embedding in reports or CI artifacts.
+pub(crate) struct SvgRenderer;
+
+impl Renderer for SvgRenderer {
+    fn render(
+        &self,
+        intervals: &[Interval],
+        layout: &LaneLayout,
+        w: &mut dyn Write,
+    ) -> Result<(), Box<dyn error::Error>> {
+        writeln!(
+            w,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}">"#,
+            layout.global_width, layout.global_duration,
+        )?;
+
+        for interval in intervals {
+            let top = seconds_between(layout.global_start_time, interval.start);
+            let height = seconds_between(interval.start, interval.end);
+            writeln!(
+                w,
+                r#"<rect x="{}" y="{}" width="{}" height="{}" fill="{}"><title>{}</title></rect>"#,
+                interval.lane * layout.lane_width,
+                top,
+                layout.lane_width - 1,
+                height,
+                escape_xml(&layout.colors[interval.color]),
+                escape_xml(&String::from_utf8_lossy(&interval.message)),
+            )?;
+        }
+
+        writeln!(w, "</svg>")?;
+        Ok(())
+    }
+}
+
+#[derive(Serialize)]
+struct JsonInterval<'a> {
+    color: &'a str,
+    start: String,
+    end: String,
+    msg: String,
+    top: f64,
+    height: f64,
+    lane: usize,
+    // Only set for the `--follow` NDJSON stream, whose `lane` is group-relative
+    // (see `write_json_interval_line`) rather than the absolute lane the batch
+    // renderers use.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    group: Option<usize>,
+}
+
+#[derive(Serialize)]
+struct JsonDocument<'a> {
+    start_time: String,
+    end_time: String,
+    intervals: Vec<JsonInterval<'a>>,
+}
+
+fn to_json_interval<'a>(
+    interval: &'a Interval,
+    group: Option<usize>,
+    global_start_time: NaiveDateTime,
+    colors: &'a [String],
+) -> JsonInterval<'a> {
+    JsonInterval {
+        color: &colors[interval.color],
+        start: interval.start.to_string(),
+        end: interval.end.to_string(),
+        msg: String::from_utf8_lossy(&interval.message).into_owned(),
+        top: seconds_between(global_start_time, interval.start),
+        height: seconds_between(interval.start, interval.end),
+        lane: interval.lane,
+        group,
+    }
+}
+
+/// Dumps the computed interval records plus the global time bounds as JSON, so
+/// other tools can post-process the timeline without re-parsing the log.
+pub(crate) struct JsonRenderer;
+
+impl Renderer for JsonRenderer {
+    fn render(
+        &self,
+        intervals: &[Interval],
+        layout: &LaneLayout,
+        w: &mut dyn Write,
+    ) -> Result<(), Box<dyn error::Error>> {
+        let document = JsonDocument {
+            start_time: layout.global_start_time.to_string(),
+            end_time: layout.global_end_time.to_string(),
+            intervals: intervals
+                .iter()
+                .map(|interval| {
+                    to_json_interval(interval, None, layout.global_start_time, &layout.colors)
+                })
+                .collect(),
+        };
+        serde_json::to_writer(w, &document)?;
+        Ok(())
+    }
+}
+
+/// Writes a single interval as one line of JSON (newline-delimited), for streaming
+/// consumers in `--follow` mode that can't wait for the whole document to be ready.
+/// `interval.lane` must be group-relative (not offset by the shifting group offsets
+/// `LaneAssigner::group_offsets` computes once a full batch is known) so a consumer
+/// appending blocks as they arrive doesn't need to reposition earlier ones; `group`
+/// is included so the consumer can still tell lanes in different groups apart.
+pub(crate) fn write_json_interval_line(
+    interval: &Interval,
+    group: usize,
+    global_start_time: NaiveDateTime,
+    colors: &[String],
+    w: &mut dyn Write,
+) -> Result<(), Box<dyn error::Error>> {
+    serde_json::to_writer(
+        &mut *w,
+        &to_json_interval(interval, Some(group), global_start_time, colors),
+    )?;
+    writeln!(w)?;
+    Ok(())
+}